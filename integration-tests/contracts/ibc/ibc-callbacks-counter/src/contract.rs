@@ -1,13 +1,16 @@
 use crate::error::ContractError;
 use crate::msg::*;
-use crate::state::{Counter, COUNTERS};
+use crate::state::{
+    AllowInfo, ChannelState, Counter, PendingTransfer, ADMIN, ALLOW_LIST, CHANNEL_STATE, COUNTERS,
+    PENDING_SEND, PENDING_TRANSFERS, PROCESSED,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     coins, ensure_eq, from_json, to_json_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env,
-    IbcAckCallbackMsg, IbcBasicResponse, IbcDestinationCallbackMsg, IbcDstCallback,
-    IbcSourceCallbackMsg, IbcSrcCallback, IbcTimeoutCallbackMsg, MessageInfo, Response, StdAck,
-    StdError, StdResult, Timestamp, TransferMsgBuilder, Uint128,
+    IbcAckCallbackMsg, IbcBasicResponse, IbcDestinationCallbackMsg, IbcDstCallback, IbcMsg,
+    IbcSourceCallbackMsg, IbcSrcCallback, IbcTimeoutCallbackMsg, MessageInfo, Order, Reply,
+    Response, StdAck, StdError, StdResult, SubMsg, Timestamp, TransferMsgBuilder, Uint128, Uint64,
 };
 use cw2::set_contract_version;
 use std::collections::HashMap;
@@ -17,6 +20,8 @@ use std::str::FromStr;
 const CONTRACT_NAME: &str = "callbacks_counter";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const TRANSFER_REPLY_ID: u64 = 1;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -32,6 +37,19 @@ pub fn instantiate(
     };
     COUNTERS.save(deps.storage, info.sender.clone(), &initial_counter)?;
 
+    if let Some(admin) = msg.admin {
+        ADMIN.save(deps.storage, &deps.api.addr_validate(&admin)?)?;
+    }
+    for allowed in msg.allowed {
+        ALLOW_LIST.save(
+            deps.storage,
+            (allowed.channel, allowed.denom),
+            &AllowInfo {
+                gas_limit: allowed.gas_limit,
+            },
+        )?;
+    }
+
     Ok(Response::new()
         .add_attribute("method", "instantiate")
         .add_attribute("owner", info.sender)
@@ -40,9 +58,9 @@ pub fn instantiate(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    _deps: DepsMut,
+    deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
@@ -50,36 +68,239 @@ pub fn execute(
             channel,
             amount,
             recipient,
-        } => transfer_funds(env, channel, amount, recipient),
+        } => transfer_funds(deps, env, info, channel, amount, recipient),
+        ExecuteMsg::Allow {
+            channel,
+            denom,
+            gas_limit,
+        } => execute_allow(deps, info, channel, denom, gas_limit),
+        ExecuteMsg::UpdateAdmin { admin } => execute_update_admin(deps, info, admin),
+        ExecuteMsg::SendCounterPacket {
+            channel,
+            receiver,
+            amount,
+        } => send_counter_packet(deps, env, info, channel, receiver, amount),
+    }
+}
+
+fn assert_admin(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let admin = ADMIN.may_load(deps.storage)?;
+    if admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
     }
+    Ok(())
+}
+
+pub fn execute_allow(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    denom: String,
+    gas_limit: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    ALLOW_LIST.save(
+        deps.storage,
+        (channel.clone(), denom.clone()),
+        &AllowInfo { gas_limit },
+    )?;
+    Ok(Response::new()
+        .add_attribute("action", "allow")
+        .add_attribute("channel", channel)
+        .add_attribute("denom", denom))
+}
+
+pub fn execute_update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: String,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let admin_addr = deps.api.addr_validate(&admin)?;
+    ADMIN.save(deps.storage, &admin_addr)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_admin")
+        .add_attribute("admin", admin_addr))
 }
 
 pub fn transfer_funds(
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     channel: String,
     amount: Coin,
     recipient: String,
 ) -> Result<Response, ContractError> {
+    let allow = ALLOW_LIST
+        .may_load(deps.storage, (channel.clone(), amount.denom.clone()))?
+        .ok_or_else(|| ContractError::NotAllowed {
+            channel: channel.clone(),
+            denom: amount.denom.clone(),
+        })?;
+
+    // the sender must escrow exactly the coin being transferred; it's held
+    // by the contract until the packet is acked and refunded if it times
+    // out or is rejected
+    let paid = cw_utils::must_pay(&info, &amount.denom)?;
+    if paid != amount.amount {
+        return Err(ContractError::FundsMismatch {
+            expected: amount.clone(),
+            sent: Coin {
+                denom: amount.denom.clone(),
+                amount: paid,
+            },
+        });
+    }
+
+    // bump outstanding optimistically now; it's reconciled once the source
+    // callback reports how the packet actually settled
+    CHANNEL_STATE.update(
+        deps.storage,
+        (channel.clone(), amount.denom.clone()),
+        |state| -> StdResult<_> {
+            let mut state = state.unwrap_or(ChannelState {
+                outstanding: Uint128::zero(),
+                total_sent: Uint128::zero(),
+            });
+            state.outstanding += amount.amount;
+            Ok(state)
+        },
+    )?;
+
+    let timeout = env.block.time.plus_minutes(5);
     let msg = TransferMsgBuilder::new(
         channel.to_string(),
         recipient.to_string(),
         amount.clone(),
-        env.block.time.plus_minutes(5),
+        timeout,
     )
     .with_src_callback(IbcSrcCallback {
         address: env.contract.address,
-        gas_limit: None,
+        gas_limit: allow.gas_limit.map(Uint64::from),
     })
     .build();
 
+    // the packet sequence isn't known until the `MsgTransfer` submessage
+    // replies, so stash the rest of the pending transfer here and finish
+    // registering it in `reply`
+    PENDING_SEND.save(
+        deps.storage,
+        &(
+            channel.clone(),
+            PendingTransfer {
+                sender: info.sender,
+                amount: amount.clone(),
+                recipient: recipient.clone(),
+                timeout,
+            },
+        ),
+    )?;
+
     Ok(Response::new()
-        .add_message(msg)
+        .add_submessage(SubMsg::reply_on_success(msg, TRANSFER_REPLY_ID))
         .add_attribute("action", "transfer_funds")
         .add_attribute("channel", channel)
         .add_attribute("amount", amount.to_string())
         .add_attribute("recipient", recipient))
 }
 
+/// Sends a `CounterPacketData` packet directly via `IbcMsg::SendPacket`, i.e.
+/// as this contract's own standalone IBC application rather than piggy-backing
+/// on an ICS20 transfer. This is what makes `ibc_packet_ack`/`ibc_packet_timeout`
+/// in `ibc.rs` reachable -- they only fire for packets sent this way.
+///
+/// Unlike `transfer_funds`, `amount` here is pure accounting metadata carried
+/// in the packet data, mirroring `do_ibc_packet_receive` on the other side --
+/// there's no ICS20 module underneath moving a real coin, so the contract
+/// must not escrow one either. `nonpayable` guards against a caller
+/// mistakenly attaching funds that would otherwise be stuck with no code
+/// path to release them.
+pub fn send_counter_packet(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel: String,
+    receiver: String,
+    amount: Coin,
+) -> Result<Response, ContractError> {
+    cw_utils::nonpayable(&info)?;
+
+    ALLOW_LIST
+        .may_load(deps.storage, (channel.clone(), amount.denom.clone()))?
+        .ok_or_else(|| ContractError::NotAllowed {
+            channel: channel.clone(),
+            denom: amount.denom.clone(),
+        })?;
+
+    let packet = CounterPacketData {
+        sender: info.sender.to_string(),
+        receiver: receiver.clone(),
+        denom: amount.denom.clone(),
+        amount: amount.amount,
+    };
+
+    let msg = IbcMsg::SendPacket {
+        channel_id: channel.clone(),
+        data: to_json_binary(&packet)?,
+        timeout: env.block.time.plus_minutes(5).into(),
+    };
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("action", "send_counter_packet")
+        .add_attribute("channel", channel)
+        .add_attribute("receiver", receiver)
+        .add_attribute("amount", amount.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match reply.id {
+        TRANSFER_REPLY_ID => handle_transfer_reply(deps, reply),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+fn handle_transfer_reply(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
+    let (channel, pending) = PENDING_SEND.load(deps.storage)?;
+    PENDING_SEND.remove(deps.storage);
+
+    let response = reply.result.into_result().map_err(StdError::generic_err)?;
+    let data = response
+        .msg_responses
+        .first()
+        .ok_or_else(|| StdError::generic_err("missing MsgTransfer response data"))?;
+    let sequence = parse_transfer_sequence(data.value.as_slice())?;
+
+    PENDING_TRANSFERS.save(deps.storage, (channel.clone(), sequence), &pending)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_pending_transfer")
+        .add_attribute("channel", channel)
+        .add_attribute("sequence", sequence.to_string()))
+}
+
+/// `MsgTransferResponse` has a single field, `sequence` (protobuf field 1,
+/// varint), so it's decoded by hand instead of depending on ibc-go's proto
+/// bindings for one integer.
+fn parse_transfer_sequence(data: &[u8]) -> StdResult<u64> {
+    if data.first() != Some(&0x08) {
+        return Err(StdError::generic_err(
+            "unexpected MsgTransferResponse encoding",
+        ));
+    }
+    let mut sequence = 0u64;
+    let mut shift = 0;
+    for byte in &data[1..] {
+        sequence |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(sequence);
+        }
+        shift += 7;
+    }
+    Err(StdError::generic_err("truncated MsgTransferResponse"))
+}
+
 pub mod utils {
     use cosmwasm_std::Addr;
 
@@ -116,27 +337,64 @@ pub mod utils {
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_source_callback(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     msg: IbcSourceCallbackMsg,
 ) -> StdResult<IbcBasicResponse> {
-    match msg {
-        IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg { .. }) => {
-            receive_ack(deps, env.contract.address.clone(), true)
-                .map_err(|e| StdError::generic_err(e.to_string()))?;
+    let res = match msg {
+        IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg {
+            acknowledgement,
+            original_packet,
+            ..
+        }) => {
+            let channel = original_packet.src.channel_id;
+            let sequence = original_packet.sequence;
+            if !check_and_mark_processed(deps.branch(), channel.clone(), sequence, "ack")? {
+                return Ok(already_processed_response("ibc_source_callback"));
+            }
+
+            let ack: StdAck = from_json(&acknowledgement.data)?;
+            let success = matches!(ack, StdAck::Success(_));
+            let pending = take_pending_transfer(deps.branch(), channel.clone(), sequence)?;
+            if let Some(p) = &pending {
+                reconcile_channel_state(deps.branch(), channel, p.amount.clone(), success)?;
+            }
+            let addr = pending
+                .as_ref()
+                .map(|p| p.sender.clone())
+                .unwrap_or_else(|| env.contract.address.clone());
+            let refund = (!success).then(|| pending.map(|p| p.amount)).flatten();
+            receive_ack(deps, addr, success, refund)
+                .map_err(|e| StdError::generic_err(e.to_string()))?
         }
-        IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg { .. }) => {
-            ibc_timeout(deps, env.contract.address.clone())
-                .map_err(|e| StdError::generic_err(e.to_string()))?;
+        IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg { packet, .. }) => {
+            let channel = packet.src.channel_id;
+            let sequence = packet.sequence;
+            if !check_and_mark_processed(deps.branch(), channel.clone(), sequence, "timeout")? {
+                return Ok(already_processed_response("ibc_source_callback"));
+            }
+
+            let pending = take_pending_transfer(deps.branch(), channel.clone(), sequence)?;
+            if let Some(p) = &pending {
+                reconcile_channel_state(deps.branch(), channel, p.amount.clone(), false)?;
+            }
+            let addr = pending
+                .as_ref()
+                .map(|p| p.sender.clone())
+                .unwrap_or_else(|| env.contract.address.clone());
+            let refund = pending.map(|p| p.amount);
+            ibc_timeout(deps, addr, refund).map_err(|e| StdError::generic_err(e.to_string()))?
         }
-    }
+    };
 
-    Ok(IbcBasicResponse::new().add_attribute("action", "ibc_source_callback"))
+    Ok(IbcBasicResponse::new()
+        .add_submessages(res.messages)
+        .add_attribute("action", "ibc_source_callback"))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_destination_callback(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     msg: IbcDestinationCallbackMsg,
 ) -> StdResult<IbcBasicResponse> {
@@ -145,23 +403,108 @@ pub fn ibc_destination_callback(
         "transfer", // transfer module uses this port by default
         StdError::generic_err("only want to handle transfer packets")
     );
-    ensure_eq!(
-        msg.ack.data,
-        StdAck::success(b"\x01").to_binary(), // this is how a successful transfer ack looks
-        StdError::generic_err("only want to handle successful transfers")
-    );
 
-    receive_ack(deps, env.contract.address.clone(), true)
+    if !check_and_mark_processed(
+        deps.branch(),
+        msg.packet.dest.channel_id.clone(),
+        msg.packet.sequence,
+        "dest_ack",
+    )? {
+        return Ok(already_processed_response("ibc_destination_callback"));
+    }
+
+    let ack: StdAck = from_json(&msg.ack.data)?;
+    let success = matches!(ack, StdAck::Success(_));
+
+    // the contract isn't the source of this packet, so there's no escrow
+    // of its own to refund here
+    let res = receive_ack(deps, env.contract.address.clone(), success, None)
         .map_err(|e| StdError::generic_err(e.to_string()))?;
 
-    Ok(IbcBasicResponse::new().add_attribute("action", "ibc_destination_callback"))
+    Ok(IbcBasicResponse::new()
+        .add_submessages(res.messages)
+        .add_attribute("action", "ibc_destination_callback"))
+}
+
+/// Checks whether `(channel, sequence, kind)` has already been processed
+/// and, if not, marks it as processed. Returns `false` when the caller
+/// should skip its side effects because this exact callback was already
+/// handled (e.g. a relayer retry).
+pub(crate) fn check_and_mark_processed(
+    deps: DepsMut,
+    channel: String,
+    sequence: u64,
+    kind: &str,
+) -> StdResult<bool> {
+    let key = (channel, sequence, kind.to_string());
+    if PROCESSED.has(deps.storage, key.clone()) {
+        return Ok(false);
+    }
+    PROCESSED.save(deps.storage, key, &())?;
+    Ok(true)
+}
+
+pub(crate) fn already_processed_response(action: &str) -> IbcBasicResponse {
+    IbcBasicResponse::new()
+        .add_attribute("action", action)
+        .add_attribute("already_processed", "true")
+}
+
+/// Looks up the pending transfer for `(channel, sequence)`, if any, removes
+/// it (the ack/timeout is terminal for that packet), and returns it.
+fn take_pending_transfer(
+    deps: DepsMut,
+    channel: String,
+    sequence: u64,
+) -> StdResult<Option<PendingTransfer>> {
+    let key = (channel, sequence);
+    let pending = PENDING_TRANSFERS.may_load(deps.storage, key.clone())?;
+    if pending.is_some() {
+        PENDING_TRANSFERS.remove(deps.storage, key);
+    }
+    Ok(pending)
+}
+
+/// Reconciles the optimistic `outstanding` balance bumped in
+/// `transfer_funds` once a packet's fate is known: `outstanding` always goes
+/// down (the packet is no longer in flight), and `total_sent` only advances
+/// on success.
+fn reconcile_channel_state(
+    deps: DepsMut,
+    channel: String,
+    amount: Coin,
+    success: bool,
+) -> StdResult<()> {
+    CHANNEL_STATE.update(
+        deps.storage,
+        (channel, amount.denom),
+        |state| -> StdResult<_> {
+            let mut state = state.unwrap_or(ChannelState {
+                outstanding: Uint128::zero(),
+                total_sent: Uint128::zero(),
+            });
+            state.outstanding = state.outstanding.saturating_sub(amount.amount);
+            if success {
+                state.total_sent += amount.amount;
+            }
+            Ok(state)
+        },
+    )?;
+    Ok(())
 }
 
 pub fn receive_ack(
     deps: DepsMut,
     contract: Addr,
-    _success: bool,
+    success: bool,
+    refund: Option<Coin>,
 ) -> Result<Response, ContractError> {
+    if !success {
+        // an ICS20 error ack means the transfer was rejected on the receiving
+        // side, so it behaves like a timeout for counter-keeping purposes
+        return ibc_timeout(deps, contract, refund);
+    }
+
     utils::update_counter(
         deps,
         contract,
@@ -171,20 +514,33 @@ pub fn receive_ack(
         },
         &|_counter| vec![],
     )?;
+    // the transfer succeeded, so the escrow is simply released, not refunded
     Ok(Response::new().add_attribute("action", "ack"))
 }
 
-pub fn ibc_timeout(deps: DepsMut, contract: Addr) -> Result<Response, ContractError> {
+pub fn ibc_timeout(
+    deps: DepsMut,
+    contract: Addr,
+    refund: Option<Coin>,
+) -> Result<Response, ContractError> {
     utils::update_counter(
         deps,
-        contract,
+        contract.clone(),
         &|counter| match counter {
             None => 10,
             Some(counter) => counter.count + 10,
         },
         &|_counter| vec![],
     )?;
-    Ok(Response::new().add_attribute("action", "timeout"))
+
+    let mut response = Response::new().add_attribute("action", "timeout");
+    if let Some(coin) = refund {
+        response = response.add_message(BankMsg::Send {
+            to_address: contract.to_string(),
+            amount: vec![coin],
+        });
+    }
+    Ok(response)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -192,6 +548,11 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetCount { addr } => to_json_binary(&query::count(deps, addr)?),
         QueryMsg::GetTotalFunds { addr } => to_json_binary(&query::total_funds(deps, addr)?),
+        QueryMsg::ChannelBalance { channel, denom } => {
+            to_json_binary(&query::channel_balance(deps, channel, denom)?)
+        }
+        QueryMsg::ListAllowed {} => to_json_binary(&query::list_allowed(deps)?),
+        QueryMsg::Admin {} => to_json_binary(&query::admin(deps)?),
     }
 }
 
@@ -211,4 +572,333 @@ pub mod query {
             total_funds: state.total_funds,
         })
     }
+
+    pub fn channel_balance(
+        deps: Deps,
+        channel: String,
+        denom: String,
+    ) -> StdResult<ChannelBalanceResponse> {
+        let state = CHANNEL_STATE
+            .may_load(deps.storage, (channel, denom))?
+            .unwrap_or(ChannelState {
+                outstanding: Uint128::zero(),
+                total_sent: Uint128::zero(),
+            });
+        Ok(ChannelBalanceResponse {
+            outstanding: state.outstanding,
+            total_sent: state.total_sent,
+        })
+    }
+
+    pub fn list_allowed(deps: Deps) -> StdResult<ListAllowedResponse> {
+        let allowed = ALLOW_LIST
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| {
+                let ((channel, denom), info) = item?;
+                Ok(AllowedEntry {
+                    channel,
+                    denom,
+                    gas_limit: info.gas_limit,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        Ok(ListAllowedResponse { allowed })
+    }
+
+    pub fn admin(deps: Deps) -> StdResult<AdminResponse> {
+        let admin = ADMIN.may_load(deps.storage)?;
+        Ok(AdminResponse {
+            admin: admin.map(|a| a.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{
+        CosmosMsg, IbcAckCallbackMsg, IbcAcknowledgement, IbcEndpoint, IbcPacket, IbcTimeout,
+        MsgResponse, SubMsgResponse, SubMsgResult,
+    };
+
+    fn ack_callback_msg(channel: &str, sequence: u64, ack: StdAck) -> IbcSourceCallbackMsg {
+        let packet = IbcPacket::new(
+            Binary::default(),
+            IbcEndpoint {
+                port_id: "transfer".to_string(),
+                channel_id: channel.to_string(),
+            },
+            IbcEndpoint {
+                port_id: "transfer".to_string(),
+                channel_id: "channel-1".to_string(),
+            },
+            sequence,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(0)),
+        );
+        IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg::new(
+            IbcAcknowledgement::new(ack.to_binary()),
+            packet,
+            Addr::unchecked("relayer"),
+        ))
+    }
+
+    #[test]
+    fn transfer_funds_rejects_fund_mismatch() {
+        let mut deps = mock_dependencies();
+        ALLOW_LIST
+            .save(
+                deps.as_mut().storage,
+                ("channel-0".to_string(), "uatom".to_string()),
+                &AllowInfo { gas_limit: None },
+            )
+            .unwrap();
+
+        let err = transfer_funds(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &coins(50, "uatom")),
+            "channel-0".to_string(),
+            Coin::new(100u128, "uatom"),
+            "recipient".to_string(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::FundsMismatch { .. }));
+    }
+
+    #[test]
+    fn send_counter_packet_rejects_attached_funds() {
+        let mut deps = mock_dependencies();
+        ALLOW_LIST
+            .save(
+                deps.as_mut().storage,
+                ("channel-0".to_string(), "uatom".to_string()),
+                &AllowInfo { gas_limit: None },
+            )
+            .unwrap();
+
+        let err = send_counter_packet(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &coins(100, "uatom")),
+            "channel-0".to_string(),
+            "receiver".to_string(),
+            Coin::new(100u128, "uatom"),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::Payment(_)));
+    }
+
+    #[test]
+    fn send_counter_packet_rejects_channel_not_allow_listed() {
+        let mut deps = mock_dependencies();
+
+        let err = send_counter_packet(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &[]),
+            "channel-0".to_string(),
+            "receiver".to_string(),
+            Coin::new(100u128, "uatom"),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, ContractError::NotAllowed { .. }));
+    }
+
+    #[test]
+    fn send_counter_packet_sends_ibc_message_for_allowed_channel() {
+        let mut deps = mock_dependencies();
+        ALLOW_LIST
+            .save(
+                deps.as_mut().storage,
+                ("channel-0".to_string(), "uatom".to_string()),
+                &AllowInfo { gas_limit: None },
+            )
+            .unwrap();
+
+        let res = send_counter_packet(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sender", &[]),
+            "channel-0".to_string(),
+            "receiver".to_string(),
+            Coin::new(100u128, "uatom"),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            res.messages[0].msg,
+            CosmosMsg::Ibc(IbcMsg::SendPacket { .. })
+        ));
+    }
+
+    #[test]
+    fn timeout_refunds_escrowed_coin_to_sender() {
+        let mut deps = mock_dependencies();
+        let sender = Addr::unchecked("sender");
+        let refund = Coin::new(100u128, "uatom");
+
+        let res = ibc_timeout(deps.as_mut(), sender.clone(), Some(refund.clone())).unwrap();
+
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![refund],
+            })
+        );
+        assert_eq!(COUNTERS.load(deps.as_ref().storage, sender).unwrap().count, 10);
+    }
+
+    #[test]
+    fn error_ack_refunds_like_timeout() {
+        let mut deps = mock_dependencies();
+        let sender = Addr::unchecked("sender");
+        let refund = Coin::new(100u128, "uatom");
+
+        let res = receive_ack(deps.as_mut(), sender.clone(), false, Some(refund.clone())).unwrap();
+
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![refund],
+            })
+        );
+        assert_eq!(COUNTERS.load(deps.as_ref().storage, sender).unwrap().count, 10);
+    }
+
+    #[test]
+    fn success_ack_releases_escrow_without_refund() {
+        let mut deps = mock_dependencies();
+        let sender = Addr::unchecked("sender");
+
+        let res = receive_ack(
+            deps.as_mut(),
+            sender.clone(),
+            true,
+            Some(Coin::new(100u128, "uatom")),
+        )
+        .unwrap();
+
+        assert!(res.messages.is_empty());
+        assert_eq!(COUNTERS.load(deps.as_ref().storage, sender).unwrap().count, 1);
+    }
+
+    #[test]
+    fn replayed_source_ack_does_not_double_refund_or_bump_counter() {
+        let mut deps = mock_dependencies();
+        let sender = Addr::unchecked("sender");
+        let channel = "channel-0".to_string();
+        let sequence = 42u64;
+        let amount = Coin::new(100u128, "uatom");
+
+        PENDING_TRANSFERS
+            .save(
+                deps.as_mut().storage,
+                (channel.clone(), sequence),
+                &PendingTransfer {
+                    sender: sender.clone(),
+                    amount: amount.clone(),
+                    recipient: "recipient".to_string(),
+                    timeout: mock_env().block.time,
+                },
+            )
+            .unwrap();
+
+        let msg = ack_callback_msg(&channel, sequence, StdAck::error("failed"));
+
+        let first = ibc_source_callback(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        assert_eq!(
+            first.attributes,
+            vec![("action", "ibc_source_callback")]
+        );
+        assert_eq!(
+            first.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: sender.to_string(),
+                amount: vec![amount],
+            })
+        );
+        assert_eq!(COUNTERS.load(deps.as_ref().storage, sender.clone()).unwrap().count, 10);
+
+        let second = ibc_source_callback(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(second.messages.is_empty());
+        assert_eq!(
+            second.attributes,
+            vec![
+                ("action", "ibc_source_callback"),
+                ("already_processed", "true")
+            ]
+        );
+        assert_eq!(COUNTERS.load(deps.as_ref().storage, sender).unwrap().count, 10);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn reply_registers_pending_transfer_with_decoded_sequence() {
+        let mut deps = mock_dependencies();
+        let channel = "channel-0".to_string();
+        let pending = PendingTransfer {
+            sender: Addr::unchecked("sender"),
+            amount: Coin::new(100u128, "uatom"),
+            recipient: "recipient".to_string(),
+            timeout: mock_env().block.time,
+        };
+        PENDING_SEND
+            .save(deps.as_mut().storage, &(channel.clone(), pending.clone()))
+            .unwrap();
+
+        // sequence 300 needs a two-byte varint: 0xAC 0x02
+        let reply = Reply {
+            id: TRANSFER_REPLY_ID,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+                msg_responses: vec![MsgResponse {
+                    type_url: "/ibc.applications.transfer.v1.MsgTransferResponse".to_string(),
+                    value: Binary::from(vec![0x08, 0xAC, 0x02]),
+                }],
+            }),
+        };
+
+        let res = handle_transfer_reply(deps.as_mut(), reply).unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                ("action", "register_pending_transfer"),
+                ("channel", channel.as_str()),
+                ("sequence", "300"),
+            ]
+        );
+
+        let stored = PENDING_TRANSFERS
+            .load(deps.as_ref().storage, (channel, 300))
+            .unwrap();
+        assert_eq!(stored, pending);
+        assert!(PENDING_SEND
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_transfer_sequence_decodes_multi_byte_varint() {
+        assert_eq!(parse_transfer_sequence(&[0x08, 0xAC, 0x02]).unwrap(), 300);
+    }
+
+    #[test]
+    fn parse_transfer_sequence_rejects_wrong_field_tag() {
+        assert!(parse_transfer_sequence(&[0x10, 0x01]).is_err());
+    }
+
+    #[test]
+    fn parse_transfer_sequence_rejects_truncated_varint() {
+        assert!(parse_transfer_sequence(&[0x08, 0xAC]).is_err());
+    }
 }
@@ -0,0 +1,33 @@
+use cosmwasm_std::{Coin, IbcOrder, StdError};
+use cw_utils::PaymentError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error(transparent)]
+    Payment(#[from] PaymentError),
+
+    #[error("unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("sent funds {sent} don't match the requested transfer amount {expected}")]
+    FundsMismatch { expected: Coin, sent: Coin },
+
+    #[error("invalid IBC channel version: got {version}, expected {expected}")]
+    InvalidIbcVersion { version: String, expected: String },
+
+    #[error("invalid IBC channel ordering: got {order:?}, expected {expected:?}")]
+    InvalidIbcOrdering {
+        order: IbcOrder,
+        expected: IbcOrder,
+    },
+
+    #[error("channel {channel} / denom {denom} is not allow-listed for transfers")]
+    NotAllowed { channel: String, denom: String },
+
+    #[error("unauthorized")]
+    Unauthorized {},
+}
@@ -0,0 +1,240 @@
+use crate::contract::{
+    already_processed_response, check_and_mark_processed, ibc_timeout, receive_ack, utils,
+};
+use crate::error::ContractError;
+use crate::msg::CounterPacketData;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_json, Coin, Coins, DepsMut, Env, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, StdAck,
+};
+
+/// Version and ordering this contract's standalone IBC application speaks,
+/// separate from the ICS20 channels it piggy-backs on via ibc-callbacks.
+pub const IBC_APP_VERSION: &str = "counter-1";
+pub const IBC_ORDERING: IbcOrder = IbcOrder::Unordered;
+
+fn enforce_order_and_version(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> Result<(), ContractError> {
+    if channel.version != IBC_APP_VERSION {
+        return Err(ContractError::InvalidIbcVersion {
+            version: channel.version.clone(),
+            expected: IBC_APP_VERSION.to_string(),
+        });
+    }
+    if let Some(counterparty_version) = counterparty_version {
+        if counterparty_version != IBC_APP_VERSION {
+            return Err(ContractError::InvalidIbcVersion {
+                version: counterparty_version.to_string(),
+                expected: IBC_APP_VERSION.to_string(),
+            });
+        }
+    }
+    if channel.order != IBC_ORDERING {
+        return Err(ContractError::InvalidIbcOrdering {
+            order: channel.order.clone(),
+            expected: IBC_ORDERING,
+        });
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> Result<IbcChannelOpenResponse, ContractError> {
+    enforce_order_and_version(msg.channel(), msg.counterparty_version())?;
+    // no custom ack negotiation needed, just confirm the version/ordering
+    Ok(None)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    enforce_order_and_version(msg.channel(), msg.counterparty_version())?;
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_channel_connect")
+        .add_attribute("channel_id", &msg.channel().endpoint.channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    _deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_channel_close")
+        .add_attribute("channel_id", &msg.channel().endpoint.channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    do_ibc_packet_receive(deps, msg).or_else(|err| {
+        Ok(IbcReceiveResponse::new(StdAck::error(err.to_string()).to_binary())
+            .add_attribute("action", "ibc_packet_receive")
+            .add_attribute("success", "false")
+            .add_attribute("error", err.to_string()))
+    })
+}
+
+fn do_ibc_packet_receive(
+    deps: DepsMut,
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let packet: CounterPacketData = from_json(&msg.packet.data)?;
+    let receiver = deps.api.addr_validate(&packet.receiver)?;
+    let received = Coin {
+        denom: packet.denom,
+        amount: packet.amount,
+    };
+
+    utils::update_counter(
+        deps,
+        receiver,
+        &|counter| match counter {
+            None => 1,
+            Some(counter) => counter.count + 1,
+        },
+        &|counter| {
+            let existing = counter.as_ref().map_or_else(Vec::new, |c| c.total_funds.clone());
+            let mut coins = Coins::try_from(existing).expect("total_funds has duplicate denoms");
+            coins
+                .add(received.clone())
+                .expect("total_funds overflowed");
+            coins.into_vec()
+        },
+    )?;
+
+    Ok(
+        IbcReceiveResponse::new(StdAck::success(b"\x01").to_binary())
+            .add_attribute("action", "ibc_packet_receive")
+            .add_attribute("success", "true"),
+    )
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    mut deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.original_packet.src.channel_id.clone();
+    let sequence = msg.original_packet.sequence;
+    if !check_and_mark_processed(deps.branch(), channel, sequence, "packet_ack")? {
+        return Ok(already_processed_response("ibc_packet_ack"));
+    }
+
+    let ack: StdAck = from_json(&msg.acknowledgement.data)?;
+    let success = matches!(ack, StdAck::Success(_));
+
+    let packet: CounterPacketData = from_json(&msg.original_packet.data)?;
+    let sender = deps.api.addr_validate(&packet.sender)?;
+
+    // `send_counter_packet` escrows no coin, so an error ack has nothing to refund
+    let res = receive_ack(deps, sender, success, None)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_submessages(res.messages)
+        .add_attribute("action", "ibc_packet_ack"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    mut deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let channel = msg.packet.src.channel_id.clone();
+    let sequence = msg.packet.sequence;
+    if !check_and_mark_processed(deps.branch(), channel, sequence, "packet_timeout")? {
+        return Ok(already_processed_response("ibc_packet_timeout"));
+    }
+
+    let packet: CounterPacketData = from_json(&msg.packet.data)?;
+    let sender = deps.api.addr_validate(&packet.sender)?;
+
+    // `send_counter_packet` escrows no coin, so a timeout has nothing to refund
+    let res = ibc_timeout(deps, sender, None)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_submessages(res.messages)
+        .add_attribute("action", "ibc_packet_timeout"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::COUNTERS;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{
+        to_json_binary, Addr, IbcEndpoint, IbcPacket, IbcTimeout, Timestamp, Uint128,
+    };
+
+    fn timeout_msg(sender: &Addr, channel: &str, sequence: u64) -> IbcPacketTimeoutMsg {
+        let packet = CounterPacketData {
+            sender: sender.to_string(),
+            receiver: "receiver".to_string(),
+            denom: "uatom".to_string(),
+            amount: Uint128::new(100),
+        };
+        let ibc_packet = IbcPacket::new(
+            to_json_binary(&packet).unwrap(),
+            IbcEndpoint {
+                port_id: "counter-1".to_string(),
+                channel_id: channel.to_string(),
+            },
+            IbcEndpoint {
+                port_id: "counter-1".to_string(),
+                channel_id: "channel-1".to_string(),
+            },
+            sequence,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(0)),
+        );
+        IbcPacketTimeoutMsg::new(ibc_packet, Addr::unchecked("relayer"))
+    }
+
+    #[test]
+    fn replayed_packet_timeout_does_not_refund_or_double_bump_counter() {
+        let mut deps = mock_dependencies();
+        let sender = deps.api.addr_make("sender");
+        let msg = timeout_msg(&sender, "channel-0", 1);
+
+        let res = ibc_packet_timeout(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            COUNTERS
+                .load(deps.as_ref().storage, sender.clone())
+                .unwrap()
+                .count,
+            10
+        );
+
+        let res = ibc_packet_timeout(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.attributes,
+            vec![
+                ("action", "ibc_packet_timeout"),
+                ("already_processed", "true")
+            ]
+        );
+        assert_eq!(
+            COUNTERS.load(deps.as_ref().storage, sender).unwrap().count,
+            10
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod contract;
+pub mod error;
+pub mod ibc;
+pub mod msg;
+pub mod state;
+
+pub use crate::error::ContractError;
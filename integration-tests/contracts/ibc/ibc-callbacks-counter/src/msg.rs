@@ -0,0 +1,102 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Coin, Uint128};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub count: i32,
+    pub admin: Option<String>,
+    #[serde(default)]
+    pub allowed: Vec<AllowMsg>,
+}
+
+#[cw_serde]
+pub struct AllowMsg {
+    pub channel: String,
+    pub denom: String,
+    pub gas_limit: Option<u64>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    TransferFunds {
+        channel: String,
+        amount: Coin,
+        recipient: String,
+    },
+    Allow {
+        channel: String,
+        denom: String,
+        gas_limit: Option<u64>,
+    },
+    UpdateAdmin {
+        admin: String,
+    },
+    /// Sends a `CounterPacketData` packet over `channel` as a standalone IBC
+    /// application, i.e. without going through `TransferMsgBuilder`/ibc-callbacks.
+    /// This is the counterpart that makes `ibc_packet_ack`/`ibc_packet_timeout`
+    /// in `ibc.rs` reachable.
+    SendCounterPacket {
+        channel: String,
+        receiver: String,
+        amount: Coin,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(GetCountResponse)]
+    GetCount { addr: Addr },
+    #[returns(GetTotalFundsResponse)]
+    GetTotalFunds { addr: Addr },
+    #[returns(ChannelBalanceResponse)]
+    ChannelBalance { channel: String, denom: String },
+    #[returns(ListAllowedResponse)]
+    ListAllowed {},
+    #[returns(AdminResponse)]
+    Admin {},
+}
+
+#[cw_serde]
+pub struct GetCountResponse {
+    pub count: i32,
+}
+
+#[cw_serde]
+pub struct GetTotalFundsResponse {
+    pub total_funds: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct ChannelBalanceResponse {
+    pub outstanding: Uint128,
+    pub total_sent: Uint128,
+}
+
+#[cw_serde]
+pub struct AllowedEntry {
+    pub channel: String,
+    pub denom: String,
+    pub gas_limit: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ListAllowedResponse {
+    pub allowed: Vec<AllowedEntry>,
+}
+
+#[cw_serde]
+pub struct AdminResponse {
+    pub admin: Option<String>,
+}
+
+/// Wire format carried in the `data` field of packets this contract sends
+/// and receives as a standalone IBC application (as opposed to the
+/// ibc-callbacks-driven ICS20 transfers above).
+#[cw_serde]
+pub struct CounterPacketData {
+    pub sender: String,
+    pub receiver: String,
+    pub denom: String,
+    pub amount: Uint128,
+}
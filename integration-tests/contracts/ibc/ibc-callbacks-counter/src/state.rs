@@ -0,0 +1,58 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
+
+#[cw_serde]
+pub struct Counter {
+    pub count: i32,
+    pub total_funds: Vec<Coin>,
+    pub owner: Addr,
+}
+
+pub const COUNTERS: Map<Addr, Counter> = Map::new("counters");
+
+#[cw_serde]
+pub struct PendingTransfer {
+    pub sender: Addr,
+    pub amount: Coin,
+    pub recipient: String,
+    pub timeout: Timestamp,
+}
+
+/// In-flight transfers awaiting an ack or timeout, keyed by the source
+/// channel and packet sequence so source callbacks can look up which
+/// transfer a given packet belongs to.
+pub const PENDING_TRANSFERS: Map<(String, u64), PendingTransfer> = Map::new("pending_transfers");
+
+/// Bridges `transfer_funds` and the `reply` entry point: the channel is
+/// known up front, but the packet sequence is only revealed once the
+/// `MsgTransfer` submessage replies.
+pub const PENDING_SEND: Item<(String, PendingTransfer)> = Item::new("pending_send");
+
+#[cw_serde]
+pub struct ChannelState {
+    pub outstanding: Uint128,
+    pub total_sent: Uint128,
+}
+
+/// Per-channel/denom balance accounting: `outstanding` is bumped
+/// optimistically the moment a transfer is queued in `transfer_funds`, then
+/// reconciled down once the source callback reports an ack or timeout.
+pub const CHANNEL_STATE: Map<(String, String), ChannelState> = Map::new("channel_state");
+
+/// Dedup set keyed by (channel, sequence, callback kind), ensuring a
+/// callback's counter/escrow side effects run at most once even if a
+/// relayer delivers it more than once.
+pub const PROCESSED: Map<(String, u64, String), ()> = Map::new("processed");
+
+/// Optional governance address allowed to manage the allow-list below.
+pub const ADMIN: Item<Addr> = Item::new("admin");
+
+#[cw_serde]
+pub struct AllowInfo {
+    pub gas_limit: Option<u64>,
+}
+
+/// Channels and denoms the admin has approved for `transfer_funds`; anything
+/// not listed here is rejected before a packet is ever sent.
+pub const ALLOW_LIST: Map<(String, String), AllowInfo> = Map::new("allow_list");